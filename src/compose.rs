@@ -0,0 +1,293 @@
+use std::{borrow::Cow, collections::HashMap, fs::File, io::BufWriter, path::Path};
+
+use sdl2::surface::Surface;
+
+use crate::util::StringErr;
+
+/// A composed frame as a tightly-packed, top-down RGBA8 pixel buffer.
+///
+/// This is the windowless equivalent of what the live renderer copies onto the
+/// SDL `Canvas`: the sky with the scrolling buildings blended over it.
+pub struct Frame {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Frame {
+    /// RGBA8 pixels, row-major, 4 bytes per pixel.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Applies a bloom pass: bright pixels bleed light into their surroundings.
+    ///
+    /// Pixels whose luminance exceeds `threshold` (in `0..=255`) are extracted
+    /// into a bright-mask, downsampled, blurred with a separable Gaussian of the
+    /// given `radius`, and additively composited back scaled by `intensity`.
+    /// A `radius` or `intensity` of zero leaves the frame untouched.
+    pub fn apply_glow(&mut self, threshold: f64, radius: u32, intensity: f64) {
+        if radius == 0 || intensity <= 0.0 {
+            return;
+        }
+
+        let width = self.width as usize;
+        let height = self.height as usize;
+
+        // Bright-pass at full resolution.
+        let mut bright = vec![[0.0f64; 3]; width * height];
+        for (pixel, out) in self.pixels.chunks_exact(4).zip(bright.iter_mut()) {
+            let (r, g, b) = (pixel[0] as f64, pixel[1] as f64, pixel[2] as f64);
+            let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            if luminance > threshold {
+                *out = [r, g, b];
+            }
+        }
+
+        // Downsample the mask 2x to make the blur cheaper and softer.
+        let low_width = width.div_ceil(2);
+        let low_height = height.div_ceil(2);
+        let mut low = vec![[0.0f64; 3]; low_width * low_height];
+        for y in 0..low_height {
+            for x in 0..low_width {
+                let mut sum = [0.0f64; 3];
+                let mut count = 0.0;
+                for dy in 0..2 {
+                    for dx in 0..2 {
+                        let (sx, sy) = (x * 2 + dx, y * 2 + dy);
+                        if sx < width && sy < height {
+                            let sample = bright[sy * width + sx];
+                            for channel in 0..3 {
+                                sum[channel] += sample[channel];
+                            }
+                            count += 1.0;
+                        }
+                    }
+                }
+                if count > 0.0 {
+                    for channel in 0..3 {
+                        low[y * low_width + x][channel] = sum[channel] / count;
+                    }
+                }
+            }
+        }
+
+        // Separable Gaussian blur: horizontal then vertical.
+        let kernel = gaussian_kernel(radius);
+        let horizontal = blur(&low, low_width, low_height, &kernel, true);
+        let blurred = blur(&horizontal, low_width, low_height, &kernel, false);
+
+        // Additively composite the upsampled blur back over the frame.
+        for y in 0..height {
+            for x in 0..width {
+                let glow = blurred[(y / 2) * low_width + (x / 2)];
+                let index = (y * width + x) * 4;
+                for channel in 0..3 {
+                    let value = self.pixels[index + channel] as f64 + intensity * glow[channel];
+                    self.pixels[index + channel] = value.clamp(0.0, 255.0).round() as u8;
+                }
+            }
+        }
+    }
+}
+
+/// Builds a normalized 1-D Gaussian kernel spanning `[-radius, radius]`.
+fn gaussian_kernel(radius: u32) -> Vec<f64> {
+    let sigma = (radius as f64 / 2.0).max(1.0);
+    let radius = radius as i32;
+
+    let mut kernel: Vec<f64> = (-radius..=radius)
+        .map(|x| (-(x * x) as f64 / (2.0 * sigma * sigma)).exp())
+        .collect();
+
+    let sum: f64 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|weight| *weight /= sum);
+    kernel
+}
+
+/// Runs one pass of a separable blur over `buffer`, along the horizontal axis
+/// when `horizontal` is set and the vertical axis otherwise. Edge samples are
+/// clamped.
+fn blur(
+    buffer: &[[f64; 3]],
+    width: usize,
+    height: usize,
+    kernel: &[f64],
+    horizontal: bool,
+) -> Vec<[f64; 3]> {
+    let radius = (kernel.len() / 2) as i32;
+    let mut out = vec![[0.0f64; 3]; width * height];
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0.0f64; 3];
+            for (tap, &weight) in kernel.iter().enumerate() {
+                let offset = tap as i32 - radius;
+                let (sx, sy) = if horizontal {
+                    ((x as i32 + offset).clamp(0, width as i32 - 1), y as i32)
+                } else {
+                    (x as i32, (y as i32 + offset).clamp(0, height as i32 - 1))
+                };
+                let sample = buffer[sy as usize * width + sx as usize];
+                for channel in 0..3 {
+                    sum[channel] += sample[channel] * weight;
+                }
+            }
+            out[y * width + x] = sum;
+        }
+    }
+
+    out
+}
+
+/// Blends `top` over `bottom` with the given `alpha` in `[0, 1]`.
+fn over(top: u8, bottom: u8, alpha: f64) -> u8 {
+    (top as f64 * alpha + bottom as f64 * (1.0 - alpha)).round() as u8
+}
+
+/// Composes the buildings surface over the sky surface into an opaque RGBA8
+/// buffer. Both the windowed and the file-export paths build their frames here.
+///
+/// Both surfaces are expected to be `RGBA32` and of identical dimensions.
+pub fn compose_frame(sky: &Surface, buildings: &Surface) -> Frame {
+    let width = sky.width();
+    let height = sky.height();
+
+    let sky_pitch = sky.pitch() as usize;
+    let buildings_pitch = buildings.pitch() as usize;
+
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+
+    sky.with_lock(|sky_pixels| {
+        buildings.with_lock(|buildings_pixels| {
+            for y in 0..height as usize {
+                for x in 0..width as usize {
+                    let sky_index = y * sky_pitch + x * 4;
+                    let buildings_index = y * buildings_pitch + x * 4;
+                    let out_index = (y * width as usize + x) * 4;
+
+                    let alpha = buildings_pixels[buildings_index + 3] as f64 / 255.0;
+
+                    for channel in 0..3 {
+                        pixels[out_index + channel] = over(
+                            buildings_pixels[buildings_index + channel],
+                            sky_pixels[sky_index + channel],
+                            alpha,
+                        );
+                    }
+                    pixels[out_index + 3] = 255;
+                }
+            }
+        })
+    });
+
+    Frame {
+        width,
+        height,
+        pixels,
+    }
+}
+
+/// Writes the composed `frames` to `path` as an animated image, selecting the
+/// encoder from the file extension (`gif` or `png`/`apng`).
+pub fn write_animation(path: &Path, frames: &[Frame], fps: u32) -> Result<(), String> {
+    match path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .as_deref()
+    {
+        Some("gif") => write_gif(path, frames, fps),
+        Some("png") | Some("apng") => write_apng(path, frames, fps),
+        _ => Err(format!("Unsupported output format: {}", path.display())),
+    }
+}
+
+/// Encodes `frames` as an animated GIF, palettizing the (small) set of colors
+/// the generators actually emit into a single global palette.
+fn write_gif(path: &Path, frames: &[Frame], fps: u32) -> Result<(), String> {
+    let (width, height) = match frames.first() {
+        Some(frame) => (frame.width, frame.height),
+        None => return Err("No frames to encode".to_string()),
+    };
+
+    let (palette, lookup) = build_palette(frames)?;
+
+    // GIF delays are measured in hundredths of a second.
+    let delay = (100 / fps).max(1) as u16;
+
+    let mut file = File::create(path).string_err()?;
+    let mut encoder =
+        gif::Encoder::new(&mut file, width as u16, height as u16, &palette).string_err()?;
+    encoder.set_repeat(gif::Repeat::Infinite).string_err()?;
+
+    for frame in frames {
+        let indices: Vec<u8> = frame
+            .pixels
+            .chunks_exact(4)
+            .map(|pixel| lookup[&(pixel[0], pixel[1], pixel[2])])
+            .collect();
+
+        let mut gif_frame = gif::Frame {
+            width: width as u16,
+            height: height as u16,
+            delay,
+            buffer: Cow::Owned(indices),
+            ..Default::default()
+        };
+        gif_frame.palette = None;
+
+        encoder.write_frame(&gif_frame).string_err()?;
+    }
+
+    Ok(())
+}
+
+/// Collects the distinct RGB colors across every frame into a GIF global palette
+/// (flattened RGB triples) plus a color-to-index lookup table.
+fn build_palette(frames: &[Frame]) -> Result<(Vec<u8>, HashMap<(u8, u8, u8), u8>), String> {
+    let mut lookup = HashMap::new();
+    let mut palette = Vec::new();
+
+    for frame in frames {
+        for pixel in frame.pixels.chunks_exact(4) {
+            let color = (pixel[0], pixel[1], pixel[2]);
+            if lookup.contains_key(&color) {
+                continue;
+            }
+            if lookup.len() >= 256 {
+                return Err("Frame uses more than 256 distinct colors".to_string());
+            }
+            lookup.insert(color, lookup.len() as u8);
+            palette.extend_from_slice(&[color.0, color.1, color.2]);
+        }
+    }
+
+    Ok((palette, lookup))
+}
+
+/// Encodes `frames` as an animated PNG (APNG), keeping the full RGBA8 colors.
+fn write_apng(path: &Path, frames: &[Frame], fps: u32) -> Result<(), String> {
+    let (width, height) = match frames.first() {
+        Some(frame) => (frame.width, frame.height),
+        None => return Err("No frames to encode".to_string()),
+    };
+
+    let file = BufWriter::new(File::create(path).string_err()?);
+
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(frames.len() as u32, 0)
+        .string_err()?;
+    encoder.set_frame_delay(1, fps as u16).string_err()?;
+
+    let mut writer = encoder.write_header().string_err()?;
+    for frame in frames {
+        writer.write_image_data(&frame.pixels).string_err()?;
+    }
+    writer.finish().string_err()?;
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+/// A 3-D k-d tree over points in CIELAB space.
+///
+/// It is used to look up the palette color that is perceptually closest to a
+/// target color. Nodes can be masked out of a query (so already-used palette
+/// entries are skipped) via the `used` flag passed to [`KdTree::nearest_unused`].
+pub struct KdTree {
+    points: Vec<[f64; 3]>,
+    nodes: Vec<Node>,
+    root: Option<usize>,
+}
+
+struct Node {
+    point: usize,
+    axis: usize,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+impl KdTree {
+    /// Builds a balanced tree over `points`. The index of a point in `points` is
+    /// its payload, i.e. what [`KdTree::nearest_unused`] returns.
+    pub fn new(points: Vec<[f64; 3]>) -> Self {
+        let mut indices: Vec<usize> = (0..points.len()).collect();
+        let mut nodes = Vec::with_capacity(points.len());
+        let root = build(&points, &mut indices, 0, &mut nodes);
+        Self {
+            points,
+            nodes,
+            root,
+        }
+    }
+
+    /// Returns the index of the point nearest to `target` (by Euclidean distance)
+    /// whose `used` entry is `false`, or `None` if every point is used.
+    pub fn nearest_unused(&self, target: [f64; 3], used: &[bool]) -> Option<usize> {
+        let mut best: Option<(usize, f64)> = None;
+        self.search(self.root, target, used, &mut best);
+        best.map(|(index, _)| index)
+    }
+
+    fn search(
+        &self,
+        node: Option<usize>,
+        target: [f64; 3],
+        used: &[bool],
+        best: &mut Option<(usize, f64)>,
+    ) {
+        let node = match node {
+            Some(node) => &self.nodes[node],
+            None => return,
+        };
+
+        let point = self.points[node.point];
+
+        if !used[node.point] {
+            let distance = squared_distance(point, target);
+            if best.map_or(true, |(_, best_distance)| distance < best_distance) {
+                *best = Some((node.point, distance));
+            }
+        }
+
+        let delta = target[node.axis] - point[node.axis];
+        let (near, far) = if delta < 0.0 {
+            (node.left, node.right)
+        } else {
+            (node.right, node.left)
+        };
+
+        self.search(near, target, used, best);
+
+        // Only descend into the far subtree when the splitting plane is closer
+        // than the best match found so far.
+        if best.map_or(true, |(_, best_distance)| delta * delta < best_distance) {
+            self.search(far, target, used, best);
+        }
+    }
+}
+
+fn build(
+    points: &[[f64; 3]],
+    indices: &mut [usize],
+    depth: usize,
+    nodes: &mut Vec<Node>,
+) -> Option<usize> {
+    if indices.is_empty() {
+        return None;
+    }
+
+    let axis = depth % 3;
+    indices.sort_by(|&a, &b| points[a][axis].total_cmp(&points[b][axis]));
+
+    let median = indices.len() / 2;
+    let point = indices[median];
+
+    let (left_indices, rest) = indices.split_at_mut(median);
+    let right_indices = &mut rest[1..];
+
+    let left = build(points, left_indices, depth + 1, nodes);
+    let right = build(points, right_indices, depth + 1, nodes);
+
+    nodes.push(Node {
+        point,
+        axis,
+        left,
+        right,
+    });
+    Some(nodes.len() - 1)
+}
+
+fn squared_distance(a: [f64; 3], b: [f64; 3]) -> f64 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
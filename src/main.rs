@@ -1,9 +1,13 @@
+mod compose;
+mod kdtree;
+mod palette;
 mod skyline;
 mod util;
 
 use std::{
     fmt::Display,
     ops::Range,
+    path::{Path, PathBuf},
     str::FromStr,
     time::{Duration, Instant},
 };
@@ -16,14 +20,15 @@ use sdl2::{
     event::Event,
     pixels::{Color, PixelFormatEnum},
     rect::{Point, Rect},
-    render::{BlendMode, Canvas, Texture, TextureCreator},
+    render::{BlendMode, Canvas},
     surface::Surface,
     sys::SDL_UpperBlit,
 };
 
 use crate::{
+    palette::{default_palette, CoherentPalette},
     skyline::{skyline, Pixel},
-    util::{filled_circle, sample_poisson_disc_2d, StringErr},
+    util::{antialiased_disc, lerp_srgb_linear, sample_2d, Sampler, StringErr},
 };
 
 const HEIGHT_RANGE: Range<u32> = 5..51;
@@ -64,10 +69,25 @@ struct Args {
     #[clap(short, long, default_value_t = 12)]
     moon_radius: u32,
 
-    /// Color of the sky.
+    /// Radius of the soft glow drawn around each star. A value of `0` keeps the
+    /// classic single-pixel stars.
+    #[clap(short = 'g', long, default_value_t = 0)]
+    star_glow: u32,
+
+    /// Color of the sky. Acts as a shortcut for a flat fill, and as the default
+    /// for the gradient endpoints below.
     #[clap(short = 'S', long, default_value_t = ArgColor { r: 63, g: 63, b: 116 })]
     sky_color: ArgColor,
 
+    /// Color at the top of the sky gradient. Defaults to `--sky-color`.
+    #[clap(long)]
+    sky_color_top: Option<ArgColor>,
+
+    /// Color at the bottom (horizon) of the sky gradient. Defaults to
+    /// `--sky-color`.
+    #[clap(long)]
+    sky_color_bottom: Option<ArgColor>,
+
     /// Color of the building borders.
     #[clap(short, long, default_value_t = ArgColor { r: 0, g: 0, b: 0 })]
     border_color: ArgColor,
@@ -83,6 +103,41 @@ struct Args {
     /// Color of the windows.
     #[clap(short = 'W', long, default_value_t = ArgColor { r: 251, g: 242, b: 54 })]
     window_color: ArgColor,
+
+    /// Give neighboring windows perceptually similar colors drawn from the
+    /// window palette, producing smooth color gradients across a facade.
+    #[clap(short = 'c', long)]
+    coherent_windows: bool,
+
+    /// Candidate palette for coherent windows, as a comma-separated list of
+    /// `#rrggbb` colors. Defaults to a warm ramp of yellows, oranges and whites.
+    #[clap(short = 'P', long, value_delimiter = ',')]
+    window_palette: Vec<ArgColor>,
+
+    /// Point distribution used for stars and windows.
+    #[clap(long, default_value_t = Sampler::Poisson)]
+    sampler: Sampler,
+
+    /// Radius of the bloom/glow blur. A value of `0` disables the glow pass.
+    #[clap(long, default_value_t = 0)]
+    glow_radius: u32,
+
+    /// Luminance (`0..=255`) above which a pixel contributes to the glow.
+    #[clap(long, default_value_t = 200.0)]
+    glow_threshold: f64,
+
+    /// Strength of the glow added back onto the frame.
+    #[clap(long, default_value_t = 0.8)]
+    glow_intensity: f64,
+
+    /// Render the animation to an image file (animated GIF or APNG) instead of
+    /// opening a window.
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    /// Number of frames to render when writing to a file.
+    #[clap(long, default_value_t = 128)]
+    frames: usize,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -127,6 +182,24 @@ fn main() -> Result<(), String> {
     let args = Args::parse();
 
     let sdl_context = sdl2::init()?;
+
+    let sky = create_sky(
+        CANVAS_WIDTH,
+        CANVAS_HEIGHT,
+        args.sky_color_top.unwrap_or(args.sky_color).into(),
+        args.sky_color_bottom.unwrap_or(args.sky_color).into(),
+        args.star_color.into(),
+        args.stars,
+        args.star_distance,
+        args.moon_radius,
+        args.star_glow,
+        args.sampler,
+    )?;
+
+    if let Some(output) = args.output.clone() {
+        return render_to_file(&args, &sky, &output);
+    }
+
     let video_subsystem = sdl_context.video()?;
 
     // 0 means nearest-neighbour
@@ -146,33 +219,22 @@ fn main() -> Result<(), String> {
 
     let texture_creator = canvas.texture_creator();
 
-    let sky_texture = create_sky(
-        &texture_creator,
-        CANVAS_WIDTH,
-        CANVAS_HEIGHT,
-        args.sky_color.into(),
-        args.star_color.into(),
-        args.stars,
-        args.star_distance,
-        args.moon_radius,
-    )?;
-
     let mut buildings_canvas = create_surface_canvas(CANVAS_WIDTH, CANVAS_HEIGHT)?;
 
-    let mut buildings_texture = texture_creator
-        .create_texture_streaming(
-            buildings_canvas.surface().pixel_format_enum(),
-            CANVAS_WIDTH,
-            CANVAS_HEIGHT,
-        )
+    // The frame is composed (and optionally glowed) on the CPU, just like the
+    // headless path, then uploaded as a single texture.
+    let mut frame_texture = texture_creator
+        .create_texture_streaming(PixelFormatEnum::RGBA32, CANVAS_WIDTH, CANVAS_HEIGHT)
         .string_err()?;
-    buildings_texture.set_blend_mode(BlendMode::Blend);
+    frame_texture.set_blend_mode(BlendMode::None);
 
     let mut generator = skyline(
         HEIGHT_RANGE,
         WIDTH_RANGE,
         args.windows,
         args.window_distance,
+        args.sampler,
+        coherent_palette(&args),
     );
 
     let mut event_pump = sdl_context.event_pump()?;
@@ -202,17 +264,13 @@ fn main() -> Result<(), String> {
                 args.window_color.into(),
             )?;
 
-            buildings_canvas.surface().with_lock(|pixels| {
-                buildings_texture
-                    .update(
-                        None,
-                        pixels,
-                        buildings_canvas.surface().pitch().try_into().unwrap(),
-                    )
-                    .string_err()
-            })?;
-            canvas.copy(&sky_texture, None, None)?;
-            canvas.copy(&buildings_texture, None, None)?;
+            let mut frame = compose::compose_frame(&sky, buildings_canvas.surface());
+            frame.apply_glow(args.glow_threshold, args.glow_radius, args.glow_intensity);
+
+            frame_texture
+                .update(None, frame.pixels(), (CANVAS_WIDTH * 4) as usize)
+                .string_err()?;
+            canvas.copy(&frame_texture, None, None)?;
             canvas.present();
 
             last_frame = Instant::now();
@@ -222,42 +280,123 @@ fn main() -> Result<(), String> {
     Ok(())
 }
 
-fn create_sky<T>(
-    texture_creator: &TextureCreator<T>,
+#[allow(clippy::too_many_arguments)]
+fn create_sky(
     width: u32,
     height: u32,
-    sky_color: Color,
+    sky_top: Color,
+    sky_bottom: Color,
     star_color: Color,
     stars: usize,
     star_distance: u32,
     moon_radius: u32,
-) -> Result<Texture, String> {
+    star_glow: u32,
+    sampler: Sampler,
+) -> Result<Surface<'static>, String> {
     let mut surface = Surface::new(width, height, PixelFormatEnum::RGBA32)?;
 
-    surface.fill_rect(Rect::new(0, 0, width, height), sky_color)?;
+    // Vertical gradient, interpolated in linear-light space per scanline.
+    let top = (sky_top.r, sky_top.g, sky_top.b);
+    let bottom = (sky_bottom.r, sky_bottom.g, sky_bottom.b);
+    for row in 0..height {
+        let t = if height <= 1 {
+            0.0
+        } else {
+            row as f64 / (height - 1) as f64
+        };
+        let (r, g, b) = lerp_srgb_linear(top, bottom, t);
+        surface.fill_rect(Rect::new(0, row as i32, width, 1), Color::RGB(r, g, b))?;
+    }
 
     let mut canvas = surface.into_canvas()?;
-    canvas.set_draw_color(star_color);
+    // Coverage is folded into each pixel's alpha, so the soft limbs blend over
+    // the sky already sitting in the surface.
+    canvas.set_blend_mode(BlendMode::Blend);
 
-    for &(x, y) in sample_poisson_disc_2d(&mut thread_rng(), star_distance, width, height)
+    for &(x, y) in sample_2d(sampler, &mut thread_rng(), star_distance, width, height)
         .choose_multiple(&mut thread_rng(), stars)
     {
-        canvas.draw_point(Point::new(x.try_into().unwrap(), y.try_into().unwrap()))?;
+        if star_glow == 0 {
+            draw_coverage(&mut canvas, x as i32, y as i32, star_color, 1.0)?;
+        } else {
+            for (px, py, coverage) in antialiased_disc((x as i32, y as i32), star_glow) {
+                draw_coverage(&mut canvas, px, py, star_color, coverage)?;
+            }
+        }
     }
 
-    for (x, y) in filled_circle(MOON_CENTER, moon_radius) {
-        canvas.draw_point(Point::new(x.try_into().unwrap(), y.try_into().unwrap()))?;
+    for (x, y, coverage) in antialiased_disc(MOON_CENTER, moon_radius) {
+        draw_coverage(&mut canvas, x, y, star_color, coverage)?;
     }
 
-    let surface = canvas.into_surface();
+    Ok(canvas.into_surface())
+}
 
-    let mut texture = texture_creator
-        .create_texture_from_surface(surface)
-        .string_err()?;
+/// Draws a single pixel whose alpha is the `color`'s alpha scaled by `coverage`,
+/// letting the surface's alpha blending soften antialiased edges.
+fn draw_coverage(
+    canvas: &mut Canvas<Surface>,
+    x: i32,
+    y: i32,
+    color: Color,
+    coverage: f64,
+) -> Result<(), String> {
+    let alpha = (color.a as f64 * coverage).round() as u8;
+    canvas.set_draw_color(Color::RGBA(color.r, color.g, color.b, alpha));
+    canvas.draw_point(Point::new(x, y))
+}
+
+/// Builds the coherent-windows palette from the CLI arguments, or `None` when
+/// the mode is disabled.
+fn coherent_palette(args: &Args) -> Option<(CoherentPalette, (u8, u8, u8))> {
+    if !args.coherent_windows {
+        return None;
+    }
 
-    texture.set_blend_mode(BlendMode::None);
+    let colors: Vec<(u8, u8, u8)> = if args.window_palette.is_empty() {
+        default_palette()
+    } else {
+        args.window_palette
+            .iter()
+            .map(|color| (color.r, color.g, color.b))
+            .collect()
+    };
+
+    let seed = (args.window_color.r, args.window_color.g, args.window_color.b);
+
+    Some((CoherentPalette::new(colors), seed))
+}
+
+/// Drives the generator for `args.frames` columns without opening a window,
+/// composing each frame over the sky and encoding the result to `path`.
+fn render_to_file(args: &Args, sky: &Surface, path: &Path) -> Result<(), String> {
+    let mut buildings_canvas = create_surface_canvas(CANVAS_WIDTH, CANVAS_HEIGHT)?;
+
+    let mut generator = skyline(
+        HEIGHT_RANGE,
+        WIDTH_RANGE,
+        args.windows,
+        args.window_distance,
+        args.sampler,
+        coherent_palette(args),
+    );
+
+    let mut frames = Vec::with_capacity(args.frames);
+    for _ in 0..args.frames {
+        scroll_left(
+            &mut buildings_canvas,
+            &mut generator,
+            args.border_color.into(),
+            args.background_color.into(),
+            args.window_color.into(),
+        )?;
+
+        let mut frame = compose::compose_frame(sky, buildings_canvas.surface());
+        frame.apply_glow(args.glow_threshold, args.glow_radius, args.glow_intensity);
+        frames.push(frame);
+    }
 
-    Ok(texture)
+    compose::write_animation(path, &frames, args.fps)
 }
 
 fn create_surface_canvas(width: u32, height: u32) -> Result<Canvas<Surface<'static>>, String> {
@@ -312,7 +451,8 @@ fn scroll_left(
         let color = match pixel {
             Pixel::Background => background_color,
             Pixel::Border => border_color,
-            Pixel::Window => window_color,
+            Pixel::Window(None) => window_color,
+            Pixel::Window(Some((r, g, b))) => Color::RGB(r, g, b),
         };
 
         let point = Point::new((width - 1).try_into().unwrap(), row.try_into().unwrap());
@@ -0,0 +1,117 @@
+use crate::{kdtree::KdTree, util::rgb_to_lab};
+
+/// A palette of candidate window colors indexed in CIELAB space, used to give
+/// neighboring windows perceptually similar colors and thus a smooth gradient
+/// across a facade.
+pub struct CoherentPalette {
+    rgb: Vec<(u8, u8, u8)>,
+    lab: Vec<[f64; 3]>,
+    tree: KdTree,
+    used: Vec<bool>,
+}
+
+impl CoherentPalette {
+    pub fn new(rgb: Vec<(u8, u8, u8)>) -> Self {
+        assert!(!rgb.is_empty());
+
+        let lab: Vec<[f64; 3]> = rgb.iter().map(|&color| rgb_to_lab(color)).collect();
+        let tree = KdTree::new(lab.clone());
+        let used = vec![false; rgb.len()];
+
+        Self {
+            rgb,
+            lab,
+            tree,
+            used,
+        }
+    }
+
+    /// Colors `positions` so that neighboring windows share a perceptually close
+    /// hue. Windows are processed in scan order; each one is steered toward the
+    /// color of its nearest already-colored neighbor on screen, falling back to
+    /// `seed` for the very first window.
+    pub fn color_windows(
+        &mut self,
+        positions: &[(u32, u32)],
+        seed: (u8, u8, u8),
+    ) -> Vec<(u8, u8, u8)> {
+        let seed_lab = rgb_to_lab(seed);
+
+        let mut order: Vec<usize> = (0..positions.len()).collect();
+        order.sort_by_key(|&index| (positions[index].1, positions[index].0));
+
+        let mut chosen: Vec<Option<usize>> = vec![None; positions.len()];
+
+        for &index in &order {
+            let target = nearest_colored(positions, index, &chosen)
+                .map(|neighbor| self.lab[chosen[neighbor].unwrap()])
+                .unwrap_or(seed_lab);
+            chosen[index] = Some(self.pick(target));
+        }
+
+        chosen
+            .into_iter()
+            .map(|index| self.rgb[index.unwrap()])
+            .collect()
+    }
+
+    /// Picks the unused palette color perceptually closest to `target`, marking
+    /// it used. Once the palette is exhausted every entry is reinserted.
+    fn pick(&mut self, target: [f64; 3]) -> usize {
+        if self.used.iter().all(|&used| used) {
+            self.used.iter_mut().for_each(|used| *used = false);
+        }
+
+        let index = self
+            .tree
+            .nearest_unused(target, &self.used)
+            .expect("palette is non-empty");
+        self.used[index] = true;
+        index
+    }
+}
+
+/// Finds the window nearest to `positions[index]` in 2-D screen space that has
+/// already been colored.
+fn nearest_colored(
+    positions: &[(u32, u32)],
+    index: usize,
+    chosen: &[Option<usize>],
+) -> Option<usize> {
+    let (x, y) = positions[index];
+
+    positions
+        .iter()
+        .enumerate()
+        .filter(|&(other, _)| other != index && chosen[other].is_some())
+        .min_by(|&(_, a), &(_, b)| {
+            squared_distance((x, y), *a)
+                .cmp(&squared_distance((x, y), *b))
+        })
+        .map(|(other, _)| other)
+}
+
+fn squared_distance(a: (u32, u32), b: (u32, u32)) -> u64 {
+    let dx = a.0 as i64 - b.0 as i64;
+    let dy = a.1 as i64 - b.1 as i64;
+    (dx * dx + dy * dy) as u64
+}
+
+/// A warm ramp of yellows, oranges and whites used when no `--window-palette`
+/// is supplied.
+pub fn default_palette() -> Vec<(u8, u8, u8)> {
+    vec![
+        (255, 255, 255),
+        (255, 249, 222),
+        (255, 242, 189),
+        (255, 233, 150),
+        (255, 223, 111),
+        (255, 211, 80),
+        (255, 196, 61),
+        (255, 178, 46),
+        (255, 158, 38),
+        (247, 137, 33),
+        (233, 115, 29),
+        (214, 96, 26),
+    ]
+}
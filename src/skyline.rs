@@ -3,24 +3,29 @@ use std::{iter, ops::Range};
 use itertools::Itertools;
 use rand::prelude::*;
 
-use crate::util::sample_poisson_disc_2d;
+use crate::{
+    palette::CoherentPalette,
+    util::{sample_2d, Sampler},
+};
 
 #[derive(Debug, Clone)]
 struct Building {
     height: u32,
     width: u32,
-    windows: Vec<(u32, u32)>,
+    windows: Vec<(u32, u32, Option<(u8, u8, u8)>)>,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum Pixel {
     Background,
     Border,
-    Window,
+    /// A lit window. `Some(color)` overrides the flat `--window-color` with a
+    /// per-window color chosen by the coherent-windows mode.
+    Window(Option<(u8, u8, u8)>),
 }
 
 impl Building {
-    pub fn new(height: u32, width: u32, windows: Vec<(u32, u32)>) -> Self {
+    pub fn new(height: u32, width: u32, windows: Vec<(u32, u32, Option<(u8, u8, u8)>)>) -> Self {
         Self {
             height,
             width,
@@ -42,13 +47,13 @@ impl Building {
                 cells
             };
 
-            for &(x, y) in self.windows.iter() {
+            for &(x, y, color) in self.windows.iter() {
                 if x != col {
                     continue;
                 }
 
                 let y: usize = y.try_into().unwrap();
-                pixels[y] = Pixel::Window;
+                pixels[y] = Pixel::Window(color);
             }
 
             pixels
@@ -56,13 +61,14 @@ impl Building {
     }
 }
 
-#[derive(Debug)]
 struct RandomBuildingGenerator {
     height_range: Range<u32>,
     width_range: Range<u32>,
     max_windows: usize,
     min_window_distance: u32,
     previous_height: u32,
+    sampler: Sampler,
+    coherent: Option<(CoherentPalette, (u8, u8, u8))>,
 }
 
 impl RandomBuildingGenerator {
@@ -71,6 +77,8 @@ impl RandomBuildingGenerator {
         width_range: Range<u32>,
         max_windows: usize,
         min_window_distance: u32,
+        sampler: Sampler,
+        coherent: Option<(CoherentPalette, (u8, u8, u8))>,
     ) -> Self {
         assert!(!height_range.is_empty());
         assert!(height_range.end - height_range.start > 1);
@@ -82,17 +90,20 @@ impl RandomBuildingGenerator {
             max_windows,
             min_window_distance,
             previous_height: 0,
+            sampler,
+            coherent,
         }
     }
 }
 
 impl RandomBuildingGenerator {
-    fn gen_windows(&self, width: u32, height: u32) -> Vec<(u32, u32)> {
+    fn gen_windows(&mut self, width: u32, height: u32) -> Vec<(u32, u32, Option<(u8, u8, u8)>)> {
         if width < 5 || height < 4 {
             return vec![];
         }
 
-        sample_poisson_disc_2d(
+        let positions: Vec<(u32, u32)> = sample_2d(
+            self.sampler,
             &mut thread_rng(),
             self.min_window_distance,
             width - 4,
@@ -100,7 +111,22 @@ impl RandomBuildingGenerator {
         )
         .choose_multiple(&mut thread_rng(), self.max_windows)
         .map(|&(x, y)| (x + 2, y + 2))
-        .collect()
+        .collect();
+
+        match &mut self.coherent {
+            Some((palette, seed)) => {
+                let colors = palette.color_windows(&positions, *seed);
+                positions
+                    .into_iter()
+                    .zip(colors)
+                    .map(|((x, y), color)| (x, y, Some(color)))
+                    .collect()
+            }
+            None => positions
+                .into_iter()
+                .map(|(x, y)| (x, y, None))
+                .collect(),
+        }
     }
 }
 
@@ -131,6 +157,8 @@ pub fn skyline(
     width_range: Range<u32>,
     max_windows: usize,
     min_window_distance: u32,
+    sampler: Sampler,
+    coherent: Option<(CoherentPalette, (u8, u8, u8))>,
 ) -> impl Iterator<Item = Vec<Pixel>> {
     iter::once(vec![])
         .chain(
@@ -139,6 +167,8 @@ pub fn skyline(
                 width_range,
                 max_windows,
                 min_window_distance,
+                sampler,
+                coherent,
             )
             .map(|building| building.iter_columns())
             .flatten(),
@@ -1,10 +1,63 @@
-use std::{error::Error, f64::consts::PI};
+use std::{error::Error, f64::consts::PI, fmt::Display, str::FromStr};
 
 use itertools::iproduct;
 use rand::{distributions::Uniform, prelude::*};
 
 const MAX_TEST_SAMPLES: usize = 30;
 
+/// Strategy used to scatter points across a 2-D domain.
+#[derive(Debug, Clone, Copy)]
+pub enum Sampler {
+    Poisson,
+    Halton,
+    Jittered,
+}
+
+impl Display for Sampler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Sampler::Poisson => "poisson",
+            Sampler::Halton => "halton",
+            Sampler::Jittered => "jittered",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Sampler {
+    type Err = &'static str;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string.to_ascii_lowercase().as_str() {
+            "poisson" => Ok(Sampler::Poisson),
+            "halton" => Ok(Sampler::Halton),
+            "jittered" => Ok(Sampler::Jittered),
+            _ => Err("Expected one of: poisson, halton, jittered"),
+        }
+    }
+}
+
+/// Scatters points across a `width`×`height` domain using the chosen `sampler`,
+/// enforcing `min_distance` between samples.
+pub fn sample_2d<R: Rng + ?Sized>(
+    sampler: Sampler,
+    rng: &mut R,
+    min_distance: u32,
+    width: u32,
+    height: u32,
+) -> Vec<(u32, u32)> {
+    match sampler {
+        Sampler::Poisson => sample_poisson_disc_2d(rng, min_distance, width, height),
+        Sampler::Halton => {
+            let budget = halton_budget(min_distance, width, height);
+            reject_min_distance(sample_halton_2d(width, height, budget), min_distance)
+        }
+        Sampler::Jittered => {
+            reject_min_distance(sample_jittered_2d(rng, min_distance, width, height), min_distance)
+        }
+    }
+}
+
 pub trait StringErr<T> {
     fn string_err(self) -> Result<T, String>;
 }
@@ -98,23 +151,192 @@ pub fn sample_poisson_disc_2d<R: Rng + ?Sized>(
     samples
 }
 
-/// Generates the coordinates of all points within a circle of a given `radius`
-/// and centered at `center`.
-pub fn filled_circle(
+/// The radical inverse of `i` in the given `base`: reverse the base-`b` digits
+/// of `i` into the fraction `0.d1 d2 d3...`, the building block of the Halton
+/// sequence.
+fn radical_inverse(mut i: u32, base: u32) -> f64 {
+    let mut result = 0.0;
+    let mut fraction = 1.0 / base as f64;
+    while i > 0 {
+        result += (i % base) as f64 * fraction;
+        i /= base;
+        fraction /= base as f64;
+    }
+    result
+}
+
+/// Generates a 2-D Halton point set using the coprime bases 2 and 3, scaled to
+/// `width`×`height`.
+pub fn sample_halton_2d(width: u32, height: u32, count: usize) -> Vec<(u32, u32)> {
+    if width == 0 || height == 0 {
+        return vec![];
+    }
+
+    // The sequence skips index 0 (which maps to the origin).
+    (1..=count as u32)
+        .map(|i| {
+            let x = (radical_inverse(i, 2) * width as f64) as u32;
+            let y = (radical_inverse(i, 3) * height as f64) as u32;
+            (x.min(width - 1), y.min(height - 1))
+        })
+        .collect()
+}
+
+/// Number of Halton candidates to draw before rejection filtering, sized to
+/// roughly fill the domain at the requested spacing.
+fn halton_budget(min_distance: u32, width: u32, height: u32) -> usize {
+    let spacing = min_distance.max(1) as usize;
+    let cells = (width as usize * height as usize) / (spacing * spacing);
+    cells.max(1) * 4
+}
+
+/// Divides the domain into an `n`×`m` grid and places one uniformly random point
+/// per cell, where the cell size tracks `min_distance`.
+pub fn sample_jittered_2d<R: Rng + ?Sized>(
+    rng: &mut R,
+    min_distance: u32,
+    width: u32,
+    height: u32,
+) -> Vec<(u32, u32)> {
+    if width == 0 || height == 0 {
+        return vec![];
+    }
+
+    let cell = min_distance.max(1);
+    let columns = (width / cell).max(1);
+    let rows = (height / cell).max(1);
+
+    let cell_width = width as f64 / columns as f64;
+    let cell_height = height as f64 / rows as f64;
+
+    let mut points = Vec::with_capacity((columns * rows) as usize);
+    for row in 0..rows {
+        for column in 0..columns {
+            let x = ((column as f64 + rng.gen::<f64>()) * cell_width) as u32;
+            let y = ((row as f64 + rng.gen::<f64>()) * cell_height) as u32;
+            points.push((x.min(width - 1), y.min(height - 1)));
+        }
+    }
+    points
+}
+
+/// Greedily drops points that fall within `min_distance` of an already-kept
+/// point, the same spacing guarantee the Poisson sampler provides.
+fn reject_min_distance(points: Vec<(u32, u32)>, min_distance: u32) -> Vec<(u32, u32)> {
+    if min_distance == 0 {
+        return points;
+    }
+
+    let mut kept: Vec<(u32, u32)> = Vec::with_capacity(points.len());
+    for point in points {
+        let far_enough = kept.iter().all(|&other| {
+            let distance = ((point.0 as f64 - other.0 as f64).powi(2)
+                + (point.1 as f64 - other.1 as f64).powi(2))
+            .sqrt();
+            distance >= min_distance as f64
+        });
+        if far_enough {
+            kept.push(point);
+        }
+    }
+    kept
+}
+
+/// Converts an 8-bit sRGB component to linear light.
+fn srgb_to_linear(c: u8) -> f64 {
+    let c = c as f64 / 255.0;
+    if c > 0.04045 {
+        ((c + 0.055) / 1.055).powf(2.4)
+    } else {
+        c / 12.92
+    }
+}
+
+/// Converts a linear-light component back to an 8-bit sRGB value.
+fn linear_to_srgb(c: f64) -> u8 {
+    let c = if c > 0.0031308 {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    } else {
+        12.92 * c
+    };
+    (c * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Interpolates between two sRGB colors by `t` in `[0, 1]`, doing the blend in
+/// linear-light space so the midtones stay vivid rather than muddy.
+pub fn lerp_srgb_linear(a: (u8, u8, u8), b: (u8, u8, u8), t: f64) -> (u8, u8, u8) {
+    let mix = |x, y| linear_to_srgb(srgb_to_linear(x) * (1.0 - t) + srgb_to_linear(y) * t);
+    (mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
+
+/// Converts an 8-bit sRGB color to CIELAB (D65), the space in which we measure
+/// perceptual color distance.
+pub fn rgb_to_lab((r, g, b): (u8, u8, u8)) -> [f64; 3] {
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c > 0.04045 {
+            ((c + 0.055) / 1.055).powf(2.4)
+        } else {
+            c / 12.92
+        }
+    };
+
+    let (r, g, b) = (linear(r), linear(g), linear(b));
+
+    // Linear sRGB -> XYZ, then normalize by the D65 white point.
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: f64| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    [
+        116.0 * fy - 16.0,
+        500.0 * (fx - fy),
+        200.0 * (fy - fz),
+    ]
+}
+
+/// Generates the coordinates of all points within a disc of a given `radius`
+/// centered at `center`, together with a `coverage` in `[0, 1]` describing how
+/// much of each pixel falls inside the disc.
+///
+/// Coverage is derived from the signed distance of the pixel center to the
+/// radius boundary (`clamp(radius + 0.5 - dist, 0, 1)`), the same edge model Wu's
+/// algorithm uses, so boundary pixels come out partially lit and interior pixels
+/// fully lit. Pixels with zero coverage are skipped.
+pub fn antialiased_disc(
     center: (i32, i32),
     radius: u32,
-) -> impl Iterator<Item = (i32, i32)> {
+) -> impl Iterator<Item = (i32, i32, f64)> {
     let (center_x, center_y) = center;
 
     assert!(radius as f64 <= (i32::MAX as f64 / 2.0).sqrt());
     let radius = radius as i32;
 
-    assert!(center_x <= i32::MAX - radius as i32);
-    assert!(center_x >= i32::MIN + radius as i32);
-    assert!(center_y <= i32::MAX - radius as i32);
-    assert!(center_y >= i32::MIN + radius as i32);
+    // The antialiased edge can extend half a pixel beyond the integer radius.
+    let bound = radius + 1;
 
-    iproduct!(-radius..=radius, -radius..=radius)
-        .filter(move |(x, y)| x * x + y * y < radius * radius)
-        .map(move |(x, y)| (x + center_x, y + center_y))
+    assert!(center_x <= i32::MAX - bound);
+    assert!(center_x >= i32::MIN + bound);
+    assert!(center_y <= i32::MAX - bound);
+    assert!(center_y >= i32::MIN + bound);
+
+    iproduct!(-bound..=bound, -bound..=bound).filter_map(move |(x, y)| {
+        let dist = ((x * x + y * y) as f64).sqrt();
+        let coverage = (radius as f64 + 0.5 - dist).clamp(0.0, 1.0);
+        if coverage <= 0.0 {
+            None
+        } else {
+            Some((x + center_x, y + center_y, coverage))
+        }
+    })
 }